@@ -1,30 +1,46 @@
+use super::vec_with_initialized::{slice_assume_init_mut, VecWithInitialized};
 use futures_core::future::Future;
 use futures_core::task::{Waker, Poll};
 use futures_io::AsyncRead;
 use std::io;
+use std::marker::PhantomPinned;
+use std::mem::{self, MaybeUninit};
 use std::pin::Pin;
 use std::vec::Vec;
 
 /// Future for the [`read_to_end`](super::AsyncReadExt::read_to_end) method.
+///
+/// Unlike a plain `&mut R`, `reader` here must already be pinned by the
+/// caller: for an `R: Unpin` reader that's just `Pin::new(reader)`, but for
+/// an `R: !Unpin` reader it has to come from wherever `R` is already
+/// guaranteed not to move again (e.g. a `self: Pin<&mut Self>` in a
+/// manually-pinned, self-referential `AsyncRead` impl). We only ever
+/// reborrow it, never fabricate a new `Pin` out of an unpinned reference —
+/// doing that would let the original owner move `R` the moment this future
+/// is dropped, invalidating any self-pointers `R` set up under its pin
+/// guarantee.
 #[derive(Debug)]
-pub struct ReadToEnd<'a, R: ?Sized + Unpin> {
-    reader: &'a mut R,
+pub struct ReadToEnd<'a, R: ?Sized> {
+    reader: Pin<&'a mut R>,
     buf: &'a mut Vec<u8>,
+    // The number of bytes appended to `buf` so far in this operation, which
+    // may be less than `buf.len()` if `buf` was non-empty when we started.
+    read: usize,
+    // Whether we've already probed once (see `read_to_end_internal` below).
+    // Lives here, not as a loop-local, so it survives across separate
+    // `poll` calls instead of being forgotten on every `Poll::Pending`.
+    probed: bool,
+    // Nothing here is structurally pinned beyond `reader`'s own `Pin`, but
+    // keep this future `!Unpin` anyway: a `!Unpin` `R` is exactly the case
+    // this type exists to support, and leaking `Unpin` back out would let
+    // callers move `ReadToEnd` itself in ways that are easy to get wrong
+    // when composing it into a larger self-referential future.
+    _pin: PhantomPinned,
 }
 
-impl<R: ?Sized + Unpin> Unpin for ReadToEnd<'_, R> {}
-
-impl<'a, R: AsyncRead + ?Sized + Unpin> ReadToEnd<'a, R> {
-    pub(super) fn new(reader: &'a mut R, buf: &'a mut Vec<u8>) -> Self {
-        ReadToEnd { reader, buf }
-    }
-}
-
-struct Guard<'a> { buf: &'a mut Vec<u8>, len: usize }
-
-impl Drop for Guard<'_> {
-    fn drop(&mut self) {
-        unsafe { self.buf.set_len(self.len); }
+impl<'a, R: AsyncRead + ?Sized> ReadToEnd<'a, R> {
+    pub(super) fn new(reader: Pin<&'a mut R>, buf: &'a mut Vec<u8>) -> Self {
+        ReadToEnd { reader, buf, read: 0, probed: false, _pin: PhantomPinned }
     }
 }
 
@@ -37,31 +53,79 @@ impl Drop for Guard<'_> {
 //
 // Because we're extending the buffer with uninitialized data for trusted
 // readers, we need to make sure to truncate that if any of this panics.
-fn read_to_end_internal<R: AsyncRead + ?Sized>(
+//
+// When the buffer is already full, growing it (and zeroing the new
+// capacity) just to discover that the reader has nothing left would be
+// wasteful for large, pre-sized buffers. So the first time we find the
+// buffer full across the whole operation (tracked via `probed`, which the
+// caller carries across polls), we probe with a small stack buffer instead:
+// if that comes back empty we've hit EOF without ever touching the `Vec`'s
+// capacity. Once we know there's more data we fall back to the usual
+// growing behavior for the rest of the read.
+pub(super) fn read_to_end_internal<R: AsyncRead + ?Sized>(
     mut rd: Pin<&mut R>,
     waker: &Waker,
     buf: &mut Vec<u8>,
-) -> Poll<io::Result<()>> {
-    let mut g = Guard { len: buf.len(), buf };
+    read: &mut usize,
+    probed: &mut bool,
+) -> Poll<io::Result<usize>> {
+    let mut g = VecWithInitialized::new(buf, rd.initializer());
     let ret;
     loop {
-        if g.len == g.buf.len() {
-            unsafe {
-                g.buf.reserve(32);
-                let capacity = g.buf.capacity();
-                g.buf.set_len(capacity);
-                rd.initializer().initialize(&mut g.buf[g.len..]);
+        if g.is_full() {
+            if !*probed {
+                let mut probe = [MaybeUninit::<u8>::uninit(); 32];
+                let probe_buf = unsafe {
+                    rd.initializer().initialize(slice_assume_init_mut(&mut probe));
+                    slice_assume_init_mut(&mut probe)
+                };
+
+                // Only latch `probed` once we actually know the outcome: a
+                // `Pending` here must leave the probe available to retry on
+                // the next poll, not fall back to the expensive grow/zero
+                // path for the rest of the read.
+                match rd.as_mut().poll_read(waker, probe_buf) {
+                    Poll::Ready(Ok(0)) => {
+                        *probed = true;
+                        ret = Poll::Ready(Ok(mem::replace(read, 0)));
+                        break;
+                    }
+                    Poll::Ready(Ok(n)) => {
+                        *probed = true;
+                        g.extend_from_slice(&probe_buf[..n], rd.initializer());
+                        *read += n;
+                        continue;
+                    }
+                    Poll::Pending => return Poll::Pending,
+                    Poll::Ready(Err(e)) => {
+                        *probed = true;
+                        // Reset the accumulator on every terminal path, not
+                        // just EOF: `read` is shared with `ReadToString`'s
+                        // scratch read, and if this future were polled
+                        // again after an error, a later success must not
+                        // silently carry over the pre-error count.
+                        *read = 0;
+                        ret = Poll::Ready(Err(e));
+                        break;
+                    }
+                }
             }
+
+            g.reserve(32, rd.initializer());
         }
 
-        match rd.as_mut().poll_read(waker, &mut g.buf[g.len..]) {
+        match rd.as_mut().poll_read(waker, g.remaining_mut()) {
             Poll::Ready(Ok(0)) => {
-                ret = Poll::Ready(Ok(()));
+                ret = Poll::Ready(Ok(mem::replace(read, 0)));
                 break;
             }
-            Poll::Ready(Ok(n)) => g.len += n,
+            Poll::Ready(Ok(n)) => {
+                g.record_read(n);
+                *read += n;
+            }
             Poll::Pending => return Poll::Pending,
             Poll::Ready(Err(e)) => {
+                *read = 0;
                 ret = Poll::Ready(Err(e));
                 break;
             }
@@ -72,12 +136,16 @@ fn read_to_end_internal<R: AsyncRead + ?Sized>(
 }
 
 impl<A> Future for ReadToEnd<'_, A>
-    where A: AsyncRead + ?Sized + Unpin,
+    where A: AsyncRead + ?Sized,
 {
-    type Output = io::Result<()>;
+    type Output = io::Result<usize>;
 
-    fn poll(mut self: Pin<&mut Self>, waker: &Waker) -> Poll<Self::Output> {
-        let this = &mut *self;
-        read_to_end_internal(Pin::new(&mut this.reader), waker, this.buf)
+    fn poll(self: Pin<&mut Self>, waker: &Waker) -> Poll<Self::Output> {
+        // Safety: we never move out of `self` or any of its fields; `buf`,
+        // `read` and `probed` are plain data, and `reader` — the one field
+        // that actually needs to stay put — is only ever reborrowed via
+        // `Pin::as_mut`, never relocated or re-pinned from scratch.
+        let this = unsafe { self.get_unchecked_mut() };
+        read_to_end_internal(this.reader.as_mut(), waker, this.buf, &mut this.read, &mut this.probed)
     }
 }
@@ -0,0 +1,87 @@
+use super::read_to_end::read_to_end_internal;
+use futures_core::future::Future;
+use futures_core::task::{Waker, Poll};
+use futures_io::AsyncRead;
+use std::io;
+use std::mem;
+use std::pin::Pin;
+use std::str;
+use std::string::String;
+use std::vec::Vec;
+
+/// Future for the [`read_to_string`](super::AsyncReadExt::read_to_string) method.
+#[derive(Debug)]
+pub struct ReadToString<'a, R: ?Sized + Unpin> {
+    reader: &'a mut R,
+    buf: &'a mut String,
+    // Scratch space the bytes are actually read into; `buf`'s original
+    // content is moved in here up front, and moved back only when this
+    // future is dropped, up to the last valid UTF-8 boundary.
+    // This keeps invalid UTF-8 from ever being observable through `buf`.
+    bytes: Vec<u8>,
+    start_len: usize,
+    read: usize,
+    probed: bool,
+}
+
+impl<R: ?Sized + Unpin> Unpin for ReadToString<'_, R> {}
+
+impl<'a, R: AsyncRead + ?Sized + Unpin> ReadToString<'a, R> {
+    pub(super) fn new(reader: &'a mut R, buf: &'a mut String) -> Self {
+        let start_len = buf.len();
+        // Move `buf`'s contents out into a `Vec<u8>`, leaving `buf` empty
+        // (and thus trivially valid UTF-8) for the duration of the read.
+        let bytes = mem::replace(buf, String::new()).into_bytes();
+        ReadToString { reader, buf, bytes, start_len, read: 0, probed: false }
+    }
+}
+
+impl<A> Drop for ReadToString<'_, A>
+    where A: ?Sized + Unpin,
+{
+    fn drop(&mut self) {
+        // Restore as much of `bytes` into `buf` as is valid UTF-8, whether
+        // this future ran to completion or was dropped early. `buf` is
+        // empty until this runs, so invalid UTF-8 is never observable
+        // through it.
+        let valid_len = match str::from_utf8(&self.bytes[self.start_len..]) {
+            Ok(s) => self.start_len + s.len(),
+            Err(e) => self.start_len + e.valid_up_to(),
+        };
+        self.bytes.truncate(valid_len);
+        // The truncated bytes are valid UTF-8 by construction.
+        unsafe {
+            self.buf.as_mut_vec().extend_from_slice(&self.bytes);
+        }
+    }
+}
+
+impl<A> Future for ReadToString<'_, A>
+    where A: AsyncRead + ?Sized + Unpin,
+{
+    type Output = io::Result<usize>;
+
+    fn poll(mut self: Pin<&mut Self>, waker: &Waker) -> Poll<Self::Output> {
+        let this = &mut *self;
+        match read_to_end_internal(
+            Pin::new(&mut this.reader),
+            waker,
+            &mut this.bytes,
+            &mut this.read,
+            &mut this.probed,
+        ) {
+            Poll::Pending => Poll::Pending,
+            Poll::Ready(Err(e)) => Poll::Ready(Err(e)),
+            Poll::Ready(Ok(_)) => {
+                if str::from_utf8(&this.bytes[this.start_len..]).is_err() {
+                    Poll::Ready(Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "stream did not contain valid UTF-8",
+                    )))
+                } else {
+                    Poll::Ready(Ok(this.bytes.len() - this.start_len))
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,141 @@
+use futures_io::Initializer;
+use std::mem::MaybeUninit;
+use std::vec::Vec;
+
+// Treat a `[MaybeUninit<u8>]` as an initialized `&mut [u8]`. The caller must
+// ensure every byte has actually been written (e.g. via `Initializer`)
+// before this is called.
+pub(super) unsafe fn slice_assume_init_mut(slice: &mut [MaybeUninit<u8>]) -> &mut [u8] {
+    &mut *(slice as *mut [MaybeUninit<u8>] as *mut [u8])
+}
+
+/// A `&mut Vec<u8>` paired with a high-water mark of how much of its spare
+/// capacity has already been initialized for the reader it's being used
+/// with.
+///
+/// While one wrapper is alive, `reserve` initializes only capacity past the
+/// mark. The mark lives only as long as the wrapper: `new` starts it at the
+/// vector's length and initializes all of the spare capacity, so a caller
+/// that builds a fresh wrapper on every poll (as `read_to_end_internal`
+/// does) runs the reader's `Initializer` over the unused tail on every poll.
+///
+/// Like the `Guard` it replaces, dropping this restores the vector's real
+/// `len` to the last-recorded valid length, so a panic partway through a
+/// read never exposes uninitialized bytes through the `Vec`.
+pub(super) struct VecWithInitialized<'a> {
+    buf: &'a mut Vec<u8>,
+    len: usize,
+    initialized: usize,
+}
+
+impl<'a> VecWithInitialized<'a> {
+    /// Wraps `buf`, establishing the "real `Vec` length always reaches
+    /// `capacity()`" invariant the other methods rely on right away — not
+    /// just once `is_full()` first trips `reserve`. Without this, a caller
+    /// that hands in spare capacity up front (`Vec::with_capacity(n)`, or
+    /// any buffer with `len < capacity`) would see `is_full()` return
+    /// `false`, so nothing reserves, and `remaining_mut()` would hand out an
+    /// empty slice instead of the actual spare capacity.
+    pub(super) fn new(buf: &'a mut Vec<u8>, initializer: Initializer) -> Self {
+        let len = buf.len();
+        let mut this = VecWithInitialized { buf, len, initialized: len };
+        this.reserve(0, initializer);
+        this
+    }
+
+    pub(super) fn is_full(&self) -> bool {
+        self.len == self.buf.capacity()
+    }
+
+    pub(super) fn record_read(&mut self, n: usize) {
+        self.len += n;
+    }
+
+    /// Reserves `additional` bytes past the current length, initializing
+    /// only the portion of the new capacity that hasn't already been
+    /// initialized by a previous call, and returns a slice covering the
+    /// whole (now fully-initialized) spare capacity.
+    pub(super) fn reserve(&mut self, additional: usize, initializer: Initializer) -> &mut [u8] {
+        self.buf.reserve(additional);
+        let capacity = self.buf.capacity();
+        unsafe {
+            self.buf.set_len(capacity);
+        }
+
+        if self.initialized < capacity {
+            initializer.initialize(&mut self.buf[self.initialized..capacity]);
+            self.initialized = capacity;
+        }
+
+        &mut self.buf[self.len..capacity]
+    }
+
+    /// Returns the writable slice from the current length to the end of the
+    /// already-initialized spare capacity, without growing the vector.
+    pub(super) fn remaining_mut(&mut self) -> &mut [u8] {
+        &mut self.buf[self.len..]
+    }
+
+    /// Appends already-initialized bytes directly instead of reading them
+    /// into reserved spare capacity, then re-syncs via `reserve(0, ..)`.
+    /// Used by the small-probe-read fast path, which has the bytes in hand
+    /// already.
+    pub(super) fn extend_from_slice(&mut self, extra: &[u8], initializer: Initializer) {
+        self.buf.truncate(self.len);
+        self.buf.extend_from_slice(extra);
+        self.len = self.buf.len();
+
+        // `extra` is real data, not filler, so it counts as already
+        // initialized — mark it as such *before* calling `reserve` below,
+        // otherwise `reserve` sees `[self.initialized..self.len)` as
+        // unwritten spare capacity and zeroes over the bytes we just
+        // appended.
+        if self.initialized < self.len {
+            self.initialized = self.len;
+        }
+
+        // `Vec::extend_from_slice` only grows the real length up to `len`,
+        // not up to the (possibly larger) capacity it just allocated. Every
+        // other method here maintains the invariant that the real `Vec`
+        // length always reaches `capacity()` while this wrapper is alive,
+        // so re-sync it the same way `reserve` does — otherwise
+        // `remaining_mut` can hand out an empty slice on the next call even
+        // though there's spare capacity, and an empty `poll_read` buffer
+        // reads as EOF to any well-behaved reader.
+        self.reserve(0, initializer);
+    }
+}
+
+impl Drop for VecWithInitialized<'_> {
+    fn drop(&mut self) {
+        unsafe {
+            self.buf.set_len(self.len);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::VecWithInitialized;
+    use futures_io::Initializer;
+
+    #[test]
+    fn new_exposes_spare_capacity_on_a_pre_sized_buffer() {
+        let mut buf = Vec::with_capacity(64);
+        let mut g = VecWithInitialized::new(&mut buf, Initializer::zeroing());
+
+        assert!(!g.is_full());
+        assert_eq!(g.remaining_mut().len(), 64);
+    }
+
+    #[test]
+    fn extend_from_slice_preserves_the_appended_bytes() {
+        let mut buf = Vec::new();
+        let mut g = VecWithInitialized::new(&mut buf, Initializer::zeroing());
+
+        g.extend_from_slice(b"hello", Initializer::zeroing());
+        drop(g);
+
+        assert_eq!(buf, b"hello");
+    }
+}